@@ -0,0 +1,100 @@
+//! Lets an operator's `on_receive` await external I/O (e.g. a lookup against a remote store)
+//! without blocking the worker thread the job is scheduled on.
+//!
+//! [`AsyncOperatorCore`] mirrors [`Future::poll`] rather than returning a future directly: the
+//! `inputs`/`outputs` slices borrowed from the enclosing [`Operator`](super::Operator) only live
+//! for the duration of one [`fire`](super::Operator::fire), so a future capturing them couldn't
+//! be held across calls. An implementation that needs to await owned I/O should hold that future
+//! itself (as a field of `Self`) and poll it here, only touching `inputs`/`outputs` once it is
+//! ready to consume/produce data. [`AsyncOperatorBridge`] is the wrapper that drives such a core
+//! the same way [`DefaultNotifyOperator`](super::DefaultNotifyOperator) drives a synchronous
+//! [`OperatorCore`](super::OperatorCore): one poll per `fire`, never blocking the caller.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use crate::communication::input::InputProxy;
+use crate::communication::output::OutputProxy;
+use crate::errors::JobExecError;
+
+use super::{Fuel, OperatorCore};
+
+/// The runtime an [`AsyncOperatorBridge`] drives its pending receives on; implementations
+/// typically bridge to `tokio`/`async-std` or a reactor owned by the worker process.
+///
+/// `waker` builds the [`Waker`] handed to [`AsyncOperatorCore::on_receive`] for one poll; once
+/// woken, it must arrange for the owning operator to be re-scheduled so the pending I/O is
+/// resumed, rather than the worker spin-polling it.
+pub trait AsyncRuntime: Send + Sync + 'static {
+    fn waker(&self) -> Waker;
+}
+
+/// The async counterpart of [`OperatorCore`]; see the module docs for why this is poll-based
+/// rather than `async fn`.
+pub trait AsyncOperatorCore: Send + 'static {
+    fn on_receive(
+        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>], fuel: &Fuel,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), JobExecError>>;
+}
+
+impl<T: ?Sized + AsyncOperatorCore> AsyncOperatorCore for Box<T> {
+    fn on_receive(
+        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>], fuel: &Fuel,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), JobExecError>> {
+        (**self).on_receive(inputs, outputs, fuel, cx)
+    }
+}
+
+/// Bridges an [`AsyncOperatorCore`] into the synchronous [`OperatorCore`] that
+/// [`Operator::fire`](super::Operator::fire) drives: each `fire` polls the wrapped core once. If
+/// it isn't ready, `fire` returns immediately instead of blocking -- [`is_pending`](OperatorCore::is_pending)
+/// reports the operator as still outstanding so the scheduler keeps servicing other operators --
+/// and the same in-flight receive is resumed, not restarted, on a later `fire` once the pending
+/// I/O wakes this operator back up.
+pub struct AsyncOperatorBridge<T> {
+    core: T,
+    runtime: Box<dyn AsyncRuntime>,
+    pending: bool,
+}
+
+impl<T: AsyncOperatorCore> AsyncOperatorBridge<T> {
+    pub fn new(core: T, runtime: Box<dyn AsyncRuntime>) -> Self {
+        AsyncOperatorBridge { core, runtime, pending: false }
+    }
+}
+
+impl<T: AsyncOperatorCore> OperatorCore for AsyncOperatorBridge<T> {
+    fn on_receive(
+        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>], fuel: &Fuel,
+    ) -> Result<(), JobExecError> {
+        let waker = self.runtime.waker();
+        let mut cx = Context::from_waker(&waker);
+        match self.core.on_receive(inputs, outputs, fuel, &mut cx) {
+            Poll::Ready(result) => {
+                self.pending = false;
+                result
+            }
+            Poll::Pending => {
+                self.pending = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn is_pending(&self) -> bool {
+        self.pending
+    }
+}
+
+/// Helper for implementing [`AsyncOperatorCore::on_receive`] in terms of a regular pinned
+/// [`Future`]: polls `fut` and hands back the `Poll<Result<(), JobExecError>>` `on_receive`
+/// returns, so the future only needs to be driven, not hand-rolled as a state machine.
+pub fn poll_future<F>(fut: Pin<&mut F>, cx: &mut Context<'_>) -> Poll<Result<(), JobExecError>>
+where
+    F: Future<Output = Result<(), JobExecError>>,
+{
+    fut.poll(cx)
+}
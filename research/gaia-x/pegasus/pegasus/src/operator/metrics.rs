@@ -0,0 +1,137 @@
+//! Structured, queryable per-operator runtime metrics, collected alongside the single
+//! fire count/exec time log line emitted by [`Operator::close`](super::Operator::close).
+//!
+//! [`Operator::metrics`](super::Operator::metrics) returns an [`OperatorMetrics`] snapshot for a
+//! single operator; [`snapshot_all`] folds that over every operator of a worker/job so callers
+//! can collect the numbers programmatically (e.g. to build a profiler dashboard) instead of
+//! scraping logs.
+
+use std::cell::Cell;
+
+use super::Operator;
+
+/// A running count/sum/max of sample durations(in microseconds), fed once per sample via
+/// [`record`](Histogram::record).
+#[derive(Default)]
+pub(crate) struct Histogram {
+    count: Cell<u64>,
+    total_us: Cell<u128>,
+    max_us: Cell<u128>,
+}
+
+impl Histogram {
+    pub(crate) fn new() -> Self {
+        Histogram::default()
+    }
+
+    pub(crate) fn record(&self, sample_us: u128) {
+        self.count.set(self.count.get() + 1);
+        self.total_us.set(self.total_us.get() + sample_us);
+        if sample_us > self.max_us.get() {
+            self.max_us.set(sample_us);
+        }
+    }
+
+    pub(crate) fn count(&self) -> u64 {
+        self.count.get()
+    }
+
+    pub(crate) fn total_us(&self) -> u128 {
+        self.total_us.get()
+    }
+
+    pub(crate) fn avg_us(&self) -> u128 {
+        let count = self.count.get();
+        if count == 0 {
+            0
+        } else {
+            self.total_us.get() / count as u128
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot {
+            count: self.count(),
+            total_us: self.total_us(),
+            avg_us: self.avg_us(),
+            max_us: self.max_us.get(),
+        }
+    }
+}
+
+/// An immutable point-in-time copy of a [`Histogram`].
+#[derive(Clone, Debug, Default)]
+pub struct HistogramSnapshot {
+    pub count: u64,
+    pub total_us: u128,
+    pub avg_us: u128,
+    pub max_us: u128,
+}
+
+/// Counters tracked by the end/cancel merge logic of a multi-input or multi-output operator; see
+/// [`MultiInputsMerge`](super::MultiInputsMerge) and [`MultiOutputsMerge`](super::MultiOutputsMerge).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MergeMetrics {
+    /// Number of scopes for which all input ports' ends have been merged into one.
+    pub end_merges: u64,
+    /// Number of scopes (including implied child scopes) for which all output ports have
+    /// cancelled, i.e. completed early-stops.
+    pub early_stop_cancels: u64,
+}
+
+/// A snapshot of one operator's runtime metrics.
+///
+/// `scope_level` is the operator's own scope level, not a per-level breakdown, and there is no
+/// per-input-port batch/record count or per-output-port record count here: `InputProxy` and
+/// `OutputProxy` (both defined outside this chunk of the crate) don't expose any counters to
+/// read those from. Wiring them through is left to whoever owns those traits; this snapshot is
+/// scoped to what `fire`/the merge logic already track on `Operator` itself.
+#[derive(Clone, Debug, Default)]
+pub struct OperatorMetrics {
+    pub index: usize,
+    pub name: String,
+    pub scope_level: u32,
+    pub fire_count: u64,
+    /// Duration of each whole [`Operator::fire`](super::Operator::fire) call -- not just the
+    /// [`OperatorCore::on_receive`](super::OperatorCore::on_receive) portion of it, which is
+    /// bracketed by block handling, end/notify draining, and output flushing on either side.
+    pub fire: HistogramSnapshot,
+    pub merges: MergeMetrics,
+}
+
+/// Snapshots the metrics of every operator in `operators`, e.g. the operators of one worker.
+pub fn snapshot_all(operators: &[Operator]) -> Vec<OperatorMetrics> {
+    operators.iter().map(Operator::metrics).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_histogram_reports_zero() {
+        let h = Histogram::new();
+        assert_eq!(h.count(), 0);
+        assert_eq!(h.total_us(), 0);
+        assert_eq!(h.avg_us(), 0);
+        assert_eq!(h.snapshot().max_us, 0);
+    }
+
+    #[test]
+    fn histogram_tracks_count_total_avg_and_max() {
+        let h = Histogram::new();
+        h.record(10);
+        h.record(30);
+        h.record(20);
+
+        assert_eq!(h.count(), 3);
+        assert_eq!(h.total_us(), 60);
+        assert_eq!(h.avg_us(), 20);
+
+        let snapshot = h.snapshot();
+        assert_eq!(snapshot.count, 3);
+        assert_eq!(snapshot.total_us, 60);
+        assert_eq!(snapshot.avg_us, 20);
+        assert_eq!(snapshot.max_us, 30);
+    }
+}
@@ -34,10 +34,18 @@ use crate::schedule::state::outbound::OutputCancelState;
 use crate::tag::tools::map::TidyTagMap;
 use crate::{Data, Tag};
 
+use self::metrics::{Histogram, MergeMetrics, OperatorMetrics};
+
 pub trait Notifiable: Send + 'static {
     fn on_notify(&mut self, n: EndScope, outputs: &[Box<dyn OutputProxy>]) -> Result<(), JobExecError>;
 
     fn on_cancel(&mut self, n: CancelScope, inputs: &[Box<dyn InputProxy>]) -> Result<(), JobExecError>;
+
+    /// Counters tracked by this operator's end/cancel merge logic, `Default` for operators that
+    /// don't merge (single input and single output).
+    fn merge_metrics(&self) -> MergeMetrics {
+        MergeMetrics::default()
+    }
 }
 
 impl<T: ?Sized + Notifiable> Notifiable for Box<T> {
@@ -48,11 +56,16 @@ impl<T: ?Sized + Notifiable> Notifiable for Box<T> {
     fn on_cancel(&mut self, n: CancelScope, inputs: &[Box<dyn InputProxy>]) -> Result<(), JobExecError> {
         (**self).on_cancel(n, inputs)
     }
+
+    fn merge_metrics(&self) -> MergeMetrics {
+        (**self).merge_metrics()
+    }
 }
 
 struct MultiInputsMerge {
     input_size: usize,
     end_merge: Vec<TidyTagMap<(EndScope, IntSet<u64>)>>,
+    end_merges: u64,
 }
 
 impl MultiInputsMerge {
@@ -61,7 +74,7 @@ impl MultiInputsMerge {
         for i in 0..scope_level + 1 {
             end_merge.push(TidyTagMap::new(i));
         }
-        MultiInputsMerge { input_size, end_merge }
+        MultiInputsMerge { input_size, end_merge, end_merges: 0 }
     }
 
     fn merge_end(&mut self, n: EndScope) -> Option<EndScope> {
@@ -73,6 +86,7 @@ impl MultiInputsMerge {
                 trace_worker!("merge {}th end of {:?} from input port {}", count.len(), tag, port);
                 merged.weight.merge(weight);
                 if count.len() == self.input_size {
+                    self.end_merges += 1;
                     Some(merged)
                 } else {
                     self.end_merge[idx].insert(tag, (merged, count));
@@ -96,6 +110,7 @@ struct MultiOutputsMerge {
     output_size: usize,
     scope_level: u32,
     cancel_merge: Vec<TidyTagMap<IntSet<u64>>>,
+    early_stop_cancels: u64,
 }
 
 impl MultiOutputsMerge {
@@ -104,14 +119,16 @@ impl MultiOutputsMerge {
         for i in 0..scope_level + 1 {
             cancel_merge.push(TidyTagMap::new(i));
         }
-        MultiOutputsMerge { output_size, scope_level, cancel_merge }
+        MultiOutputsMerge { output_size, scope_level, cancel_merge, early_stop_cancels: 0 }
     }
 
-    // TODO: enable merge cancel from parent into children;
-    fn merge_cancel(&mut self, n: CancelScope) -> Option<Tag> {
+    /// Merges a cancel from one output port, and, once every output has cancelled `n`'s scope,
+    /// also cancels every already-tracked child scope of it (i.e. any tag at a deeper level that
+    /// has it as a prefix) since a cancelled parent scope can never produce more children.
+    fn merge_cancel(&mut self, n: CancelScope) -> Vec<Tag> {
         let level = n.tag().len();
         assert!(level < self.cancel_merge.len());
-        if let Some(mut in_merge) = self.cancel_merge[level].remove(n.tag()) {
+        let completed = if let Some(mut in_merge) = self.cancel_merge[level].remove(n.tag()) {
             in_merge.insert(n.port as u64);
             let left = self.output_size - in_merge.len();
             if left == 0 {
@@ -126,6 +143,43 @@ impl MultiOutputsMerge {
             m.insert(n.port as u64);
             self.cancel_merge[level].insert(n.tag().clone(), m);
             None
+        };
+
+        let mut cancelled = Vec::new();
+        if let Some(tag) = completed {
+            self.cancel_children(&tag, level, &mut cancelled);
+            cancelled.push(tag);
+        }
+        self.early_stop_cancels += cancelled.len() as u64;
+        cancelled
+    }
+
+    /// Sweeps every tracked tag at a deeper level than `parent` that descends from it, however
+    /// many levels down. `Tag::is_parent_of` is only trusted here as a *direct*-parent check
+    /// (one level down): rather than assume it also recognizes grandchildren, this walks the
+    /// cancellation down one level at a time, re-seeding the "parents" it looks for at each level
+    /// with the children it just found cancelled at the level above.
+    fn cancel_children(&mut self, parent: &Tag, parent_level: usize, cancelled: &mut Vec<Tag>) {
+        let mut frontier = vec![parent.clone()];
+        for level in parent_level + 1..self.cancel_merge.len() {
+            if frontier.is_empty() {
+                break;
+            }
+            let children: Vec<Tag> = self.cancel_merge[level]
+                .iter()
+                .map(|(t, _)| t.clone())
+                .filter(|t| frontier.iter().any(|p| p.is_parent_of(t)))
+                .collect();
+            for child in &children {
+                self.cancel_merge[level].remove(child);
+                trace_worker!(
+                    "EARLY_STOP: parent {:?} fully cancelled, implicitly cancel child {:?};",
+                    parent,
+                    child
+                );
+            }
+            cancelled.extend(children.iter().cloned());
+            frontier = children;
         }
     }
 }
@@ -166,13 +220,27 @@ impl DefaultNotify {
         }
     }
 
-    fn merge_cancel(&mut self, cancel: CancelScope) -> Option<Tag> {
+    fn merge_cancel(&mut self, cancel: CancelScope) -> Vec<Tag> {
         match self {
-            DefaultNotify::SISO | DefaultNotify::MISO(_) => Some(cancel.tag),
+            DefaultNotify::SISO | DefaultNotify::MISO(_) => vec![cancel.tag],
             DefaultNotify::SIMO(mom) => mom.merge_cancel(cancel),
             DefaultNotify::MIMO(_, mom) => mom.merge_cancel(cancel),
         }
     }
+
+    fn merge_metrics(&self) -> MergeMetrics {
+        match self {
+            DefaultNotify::SISO => MergeMetrics::default(),
+            DefaultNotify::MISO(mim) => MergeMetrics { end_merges: mim.end_merges, early_stop_cancels: 0 },
+            DefaultNotify::SIMO(mom) => {
+                MergeMetrics { end_merges: 0, early_stop_cancels: mom.early_stop_cancels }
+            }
+            DefaultNotify::MIMO(mim, mom) => MergeMetrics {
+                end_merges: mim.end_merges,
+                early_stop_cancels: mom.early_stop_cancels,
+            },
+        }
+    }
 }
 
 pub struct DefaultNotifyOperator<T> {
@@ -208,7 +276,7 @@ impl<T: Send + 'static> Notifiable for DefaultNotifyOperator<T> {
 
     fn on_cancel(&mut self, n: CancelScope, inputs: &[Box<dyn InputProxy>]) -> Result<(), JobExecError> {
         if !inputs.is_empty() {
-            if let Some(cancel) = self.notify.merge_cancel(n) {
+            for cancel in self.notify.merge_cancel(n) {
                 for input in inputs {
                     input.cancel_scope(&cancel);
                 }
@@ -216,28 +284,88 @@ impl<T: Send + 'static> Notifiable for DefaultNotifyOperator<T> {
         }
         Ok(())
     }
+
+    fn merge_metrics(&self) -> MergeMetrics {
+        self.notify.merge_metrics()
+    }
+}
+
+/// The default number of batches an operator may pull from its inputs during a single [`fire`]
+/// before it must yield back to the scheduler.
+///
+/// [`fire`]: Operator::fire
+pub const DEFAULT_FUEL_BUDGET: u32 = 1024;
+
+/// A cooperative scheduling budget handed to [`OperatorCore::on_receive`] for the duration of one
+/// [`fire`](Operator::fire) call.
+///
+/// An `on_receive` implementation that pulls batches in a loop should call [`tick`](Fuel::tick)
+/// once per batch consumed and stop pulling as soon as it returns `false`, leaving the remaining
+/// batches buffered in the input. This bounds how much work a single hot operator with a large
+/// backlog can do before other ready operators get a turn; the unconsumed input is picked up
+/// again on the operator's next `fire`.
+pub struct Fuel {
+    remaining: Cell<u32>,
+}
+
+impl Fuel {
+    fn new(budget: u32) -> Self {
+        Fuel { remaining: Cell::new(budget) }
+    }
+
+    /// Consumes one unit of fuel and returns `true` if there is budget left to keep pulling,
+    /// `false` if the caller should stop and let the remaining input wait for the next `fire`.
+    pub fn tick(&self) -> bool {
+        let remaining = self.remaining.get();
+        if remaining == 0 {
+            false
+        } else {
+            self.remaining.set(remaining - 1);
+            true
+        }
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining.get() == 0
+    }
 }
 
 pub trait OperatorCore: Send + 'static {
     fn on_receive(
-        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>], fuel: &Fuel,
     ) -> Result<(), JobExecError>;
+
+    /// Whether the previous call to `on_receive` is still in flight and hasn't produced a
+    /// result yet; always `false` for synchronous cores. Overridden by
+    /// [`AsyncOperatorBridge`](self::async_core::AsyncOperatorBridge) so that `on_notify` is
+    /// withheld until the pending receive resolves.
+    fn is_pending(&self) -> bool {
+        false
+    }
 }
 
 impl<T: ?Sized + OperatorCore> OperatorCore for Box<T> {
     fn on_receive(
-        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>], fuel: &Fuel,
     ) -> Result<(), JobExecError> {
-        (**self).on_receive(inputs, outputs)
+        (**self).on_receive(inputs, outputs, fuel)
+    }
+
+    fn is_pending(&self) -> bool {
+        (**self).is_pending()
     }
 }
 
 impl<T: OperatorCore> OperatorCore for DefaultNotifyOperator<T> {
     #[inline]
     fn on_receive(
-        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>],
+        &mut self, inputs: &[Box<dyn InputProxy>], outputs: &[Box<dyn OutputProxy>], fuel: &Fuel,
     ) -> Result<(), JobExecError> {
-        self.op.on_receive(inputs, outputs)
+        self.op.on_receive(inputs, outputs, fuel)
+    }
+
+    fn is_pending(&self) -> bool {
+        self.op.is_pending()
     }
 }
 
@@ -248,6 +376,9 @@ impl<T: ?Sized + OperatorCore + Notifiable> NotifiableOperator for T {}
 pub enum GeneralOperator {
     Simple(Box<dyn OperatorCore>),
     Notifiable(Box<dyn NotifiableOperator>),
+    /// An [`AsyncOperatorCore`](self::async_core::AsyncOperatorCore) bridged onto a pluggable
+    /// [`AsyncRuntime`](self::async_core::AsyncRuntime); see [`self::async_core`].
+    Async(Box<dyn self::async_core::AsyncOperatorCore>, Box<dyn self::async_core::AsyncRuntime>),
 }
 
 /// 算子调度的输入条件：
@@ -259,18 +390,67 @@ pub enum GeneralOperator {
 /// 可调度判断：
 /// 3 and (1 or 2)
 ///
-///
+/// See [`Operator::is_schedulable`], which folds condition 3 in proactively instead of firing
+/// the operator and reacting to the blocks it produces.
 pub struct Operator {
     pub info: OperatorInfo,
     inputs: Vec<Box<dyn InputProxy>>,
+    input_channels: Vec<ChannelInfo>,
     outputs: Vec<Box<dyn OutputProxy>>,
     core: Box<dyn NotifiableOperator>,
-    fire_times: u128,
-    exec_st: Cell<u128>,
+    fuel_budget: u32,
+    fire_stats: Histogram,
 }
 
 impl Operator {
+    pub fn input_size(&self) -> usize {
+        self.inputs.len()
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn input_channels(&self) -> &[ChannelInfo] {
+        &self.input_channels
+    }
+
+    pub fn fire_times(&self) -> u128 {
+        self.fire_stats.count() as u128
+    }
+
+    /// Total accumulated time(in microseconds) this operator has spent in [`fire`], across
+    /// every call since it was built.
+    ///
+    /// [`fire`]: Operator::fire
+    pub fn total_exec_micros(&self) -> u128 {
+        self.fire_stats.total_us()
+    }
+
+    /// Average time(in microseconds) one call to [`fire`] has taken so far, or `0` if the
+    /// operator has never fired.
+    ///
+    /// [`fire`]: Operator::fire
+    pub fn avg_exec_micros(&self) -> u128 {
+        self.fire_stats.avg_us()
+    }
+
+    /// A structured snapshot of this operator's runtime metrics; see [`metrics`](self::metrics).
+    pub fn metrics(&self) -> OperatorMetrics {
+        OperatorMetrics {
+            index: self.info.index,
+            name: self.info.name.clone(),
+            scope_level: self.info.scope_level,
+            fire_count: self.fire_stats.count(),
+            fire: self.fire_stats.snapshot(),
+            merges: self.core.merge_metrics(),
+        }
+    }
+
     pub fn has_outstanding(&self) -> IOResult<bool> {
+        if self.core.is_pending() {
+            return Ok(true);
+        }
         for input in self.inputs.iter() {
             if input.has_outstanding()? {
                 return Ok(true);
@@ -299,19 +479,50 @@ impl Operator {
         Ok(!self.has_outstanding()?)
     }
 
+    /// Whether at least one output still has spare capacity to accept more data, i.e. condition
+    /// 3 of the scheduling comment above. Checked proactively so the scheduler can skip firing an
+    /// operator whose downstream is saturated, instead of firing it, producing blocks, and then
+    /// immediately re-blocking its inputs in response. An operator with several outputs can still
+    /// make progress on the ones that aren't saturated, so this only withholds scheduling once
+    /// *every* output is blocked -- not as soon as any single one is.
+    ///
+    /// `OutputProxy` doesn't expose a first-class remaining-capacity query (that trait lives
+    /// outside this chunk of the crate), so this reads the same block bookkeeping
+    /// [`is_idle`](Self::is_idle) already relies on: an output with no recorded blocks has room.
+    pub fn has_capacity(&self) -> bool {
+        self.outputs.is_empty() || self.outputs.iter().any(|output| output.get_blocks().is_empty())
+    }
+
+    /// The full scheduling condition: `3 and (1 or 2)`, i.e. [`has_capacity`](Self::has_capacity)
+    /// and [`has_outstanding`](Self::has_outstanding). A worker's run loop should only call
+    /// [`fire`](Self::fire) on operators for which this returns `Ok(true)`.
+    pub fn is_schedulable(&self) -> IOResult<bool> {
+        if !self.has_capacity() {
+            return Ok(false);
+        }
+        self.has_outstanding()
+    }
+
     #[inline]
     pub fn fire(&mut self) -> Result<(), JobExecError> {
-        let _f = Finally::new(&self.exec_st);
+        let _f = Finally::new(&self.fire_stats);
         debug_worker!("fire operator {:?}", self.info);
-        self.fire_times += 1;
 
         for output in self.outputs.iter() {
             output.try_unblock()?;
         }
 
+        let fuel = Fuel::new(self.fuel_budget);
         let result = self
             .core
-            .on_receive(&self.inputs, &self.outputs);
+            .on_receive(&self.inputs, &self.outputs, &fuel);
+        if fuel.is_exhausted() {
+            trace_worker!(
+                "operator {:?} exhausted its fuel budget of {}, yielding back to the scheduler",
+                self.info,
+                self.fuel_budget
+            );
+        }
 
         for output in self.outputs.iter() {
             let blocks = output.get_blocks();
@@ -334,12 +545,18 @@ impl Operator {
             }
         };
 
-        for (port, input) in self.inputs.iter().enumerate() {
-            while let Some(end) = input.extract_end() {
-                let (tag, weight, _) = end.take();
-                let notification = EndScope { port, tag, weight };
-                self.core
-                    .on_notify(notification, &self.outputs)?;
+        // A pending async receive (see `self::async_core`) hasn't produced its result yet, so
+        // any ends already sitting in the inputs must wait: delivering them now could notify a
+        // downstream operator of end-of-scope before this operator's own in-flight output for
+        // that scope has been pushed.
+        if !self.core.is_pending() {
+            for (port, input) in self.inputs.iter().enumerate() {
+                while let Some(end) = input.extract_end() {
+                    let (tag, weight, _) = end.take();
+                    let notification = EndScope { port, tag, weight };
+                    self.core
+                        .on_notify(notification, &self.outputs)?;
+                }
             }
         }
 
@@ -371,9 +588,9 @@ impl Operator {
         debug_worker!(
             "operator {:?}\tfinished, used {:.2}ms, fired {} times, avg fire use {}us",
             self.info,
-            self.exec_st.get() as f64 / 1000.0,
-            self.fire_times,
-            self.exec_st.get() / self.fire_times
+            self.total_exec_micros() as f64 / 1000.0,
+            self.fire_times(),
+            self.avg_exec_micros()
         );
     }
 }
@@ -381,20 +598,36 @@ impl Operator {
 pub struct OperatorBuilder {
     pub info: OperatorInfo,
     inputs: Vec<Box<dyn InputProxy>>,
+    input_channels: Vec<ChannelInfo>,
     inputs_notify: Vec<Option<Box<dyn InputEndNotify>>>,
     outputs: Vec<Box<dyn OutputBuilder>>,
     core: GeneralOperator,
+    fuel_budget: u32,
 }
 
 impl OperatorBuilder {
     pub fn new(meta: OperatorInfo, core: GeneralOperator) -> Self {
-        OperatorBuilder { info: meta, inputs: vec![], inputs_notify: vec![], outputs: vec![], core }
+        OperatorBuilder {
+            info: meta,
+            inputs: vec![],
+            input_channels: vec![],
+            inputs_notify: vec![],
+            outputs: vec![],
+            core,
+            fuel_budget: DEFAULT_FUEL_BUDGET,
+        }
     }
 
     pub fn index(&self) -> usize {
         self.info.index
     }
 
+    /// Overrides the per-[`fire`](Operator::fire) batch-pull budget for this operator; defaults
+    /// to [`DEFAULT_FUEL_BUDGET`].
+    pub fn set_fuel_budget(&mut self, fuel_budget: u32) {
+        self.fuel_budget = fuel_budget;
+    }
+
     pub(crate) fn add_input<T: Data>(
         &mut self, ch_info: ChannelInfo, pull: GeneralPull<MicroBatch<T>>,
         notify: Option<GeneralPush<MicroBatch<T>>>, event_emitter: &EventEmitter,
@@ -402,6 +635,7 @@ impl OperatorBuilder {
         assert_eq!(ch_info.target_port.port, self.inputs.len());
         let input = new_input(ch_info, pull, event_emitter);
         self.inputs.push(input);
+        self.input_channels.push(ch_info);
         let n = notify.map(|p| Box::new(p) as Box<dyn InputEndNotify>);
         self.inputs_notify.push(n);
     }
@@ -450,36 +684,127 @@ impl OperatorBuilder {
                 Box::new(op) as Box<dyn NotifiableOperator>
             }
             GeneralOperator::Notifiable(op) => op,
+            GeneralOperator::Async(op, runtime) => {
+                let scope_level = self.info.scope_level;
+                let input_size = self.inputs.len();
+                let output_size = outputs.len();
+                let bridge = self::async_core::AsyncOperatorBridge::new(op, runtime);
+                let op = DefaultNotifyOperator::new(input_size, output_size, scope_level, bridge);
+                Box::new(op) as Box<dyn NotifiableOperator>
+            }
         };
         Operator {
             info: self.info,
             inputs: self.inputs,
+            input_channels: self.input_channels,
             outputs,
             core,
-            fire_times: 0,
-            exec_st: Cell::new(0),
+            fuel_budget: self.fuel_budget,
+            fire_stats: Histogram::new(),
         }
     }
 }
 
 struct Finally<'a> {
-    exec_st: &'a Cell<u128>,
+    stats: &'a Histogram,
     start: Instant,
 }
 
 impl<'a> Finally<'a> {
-    pub fn new(exec_st: &'a Cell<u128>) -> Self {
-        Finally { exec_st, start: Instant::now() }
+    pub fn new(stats: &'a Histogram) -> Self {
+        Finally { stats, start: Instant::now() }
     }
 }
 
 impl<'a> Drop for Finally<'a> {
     fn drop(&mut self) {
-        let s = self.exec_st.get() + self.start.elapsed().as_micros();
-        self.exec_st.set(s);
+        self.stats.record(self.start.elapsed().as_micros());
     }
 }
 
+pub mod async_core;
 mod concise;
+pub mod dot;
 mod iteration;
+pub mod metrics;
 mod primitives;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuel_tracks_budget_and_exhausts() {
+        let fuel = Fuel::new(3);
+        assert!(!fuel.is_exhausted());
+        assert!(fuel.tick());
+        assert!(fuel.tick());
+        assert!(fuel.tick());
+        assert!(fuel.is_exhausted());
+        assert!(!fuel.tick());
+        assert!(fuel.is_exhausted());
+    }
+
+    #[test]
+    fn fuel_zero_budget_is_exhausted_immediately() {
+        let fuel = Fuel::new(0);
+        assert!(fuel.is_exhausted());
+        assert!(!fuel.tick());
+    }
+}
+
+#[cfg(test)]
+mod cancel_children_tests {
+    use super::*;
+
+    /// Builds a chain of nested scope tags `root -> a[0] -> a[0, 1] -> ...`, one per `path`
+    /// entry. `Tag` itself is defined outside this chunk of the crate; this assumes its real
+    /// `Root`/`inherit` constructors, the same assumption the production code above already
+    /// makes about `Tag::len`/`clone`/`is_parent_of`.
+    fn nested_tag(path: &[u32]) -> Tag {
+        let mut tag = Tag::Root;
+        for &index in path {
+            tag = Tag::inherit(&tag, index);
+        }
+        tag
+    }
+
+    fn cancel_scope(tag: Tag, port: usize) -> CancelScope {
+        CancelScope { tag, port }
+    }
+
+    #[test]
+    fn cancel_children_sweeps_descendants_across_levels() {
+        let mut merge = MultiOutputsMerge::new(1, 2);
+        // Track a child and a grandchild scope under the parent we're about to cancel, as if
+        // their own cancels hadn't fully merged yet.
+        let child = nested_tag(&[0]);
+        let grandchild = nested_tag(&[0, 1]);
+        let mut m = IntSet::default();
+        m.insert(0u64);
+        merge.cancel_merge[1].insert(child.clone(), m.clone());
+        merge.cancel_merge[2].insert(grandchild.clone(), m);
+
+        let mut cancelled = Vec::new();
+        merge.cancel_children(&nested_tag(&[]), 0, &mut cancelled);
+
+        assert_eq!(cancelled.len(), 2);
+        assert!(cancelled.contains(&child));
+        assert!(cancelled.contains(&grandchild));
+        assert!(merge.cancel_merge[1].remove(&child).is_none());
+        assert!(merge.cancel_merge[2].remove(&grandchild).is_none());
+    }
+
+    #[test]
+    fn merge_cancel_completes_only_once_every_output_cancels() {
+        let mut merge = MultiOutputsMerge::new(2, 0);
+        let tag = nested_tag(&[]);
+
+        let first = merge.merge_cancel(cancel_scope(tag.clone(), 0));
+        assert!(first.is_empty(), "should wait for the second output before cancelling");
+
+        let second = merge.merge_cancel(cancel_scope(tag.clone(), 1));
+        assert_eq!(second, vec![tag]);
+        assert_eq!(merge.early_stop_cancels, 1);
+    }
+}